@@ -16,7 +16,12 @@
 // Code should be published on github.
 
 use std::{
-    sync::{mpsc::channel, Arc, Mutex},
+    cell::UnsafeCell,
+    ops::Range,
+    sync::{
+        mpsc::{self, channel},
+        Arc, Condvar, Mutex,
+    },
     thread,
 };
 
@@ -24,10 +29,11 @@ use rayon::prelude::*;
 
 const TRESHOLD: usize = 100;
 
-pub fn splitter_with_rayon<T, R>(input: Vec<T>, f: fn(T) -> R) -> Vec<R>
+pub fn splitter_with_rayon<T, R, F>(input: Vec<T>, f: F) -> Vec<R>
 where
     T: Clone + Send + Sync,
     R: Send,
+    F: Fn(T) -> R + Send + Sync,
 {
     if input.len() < TRESHOLD {
         input.iter().map(|v| f(v.clone())).collect()
@@ -39,20 +45,116 @@ where
     }
 }
 
-pub fn splitter_no_deps<T, R>(input: Vec<T>, f: fn(T) -> R) -> Vec<R>
-where
-    T: Clone + Send + Sync + 'static,
-    R: Send + 'static,
-{
-    if input.len() < TRESHOLD {
-        input.iter().map(|v| f(v.clone())).collect()
-    } else {
+/// Default cap on live worker threads, used when callers don't care to tune it.
+fn default_max_threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// RAII permit for a `Condvar`-guarded worker-count semaphore. `acquire`
+/// blocks until the live count is below `max_threads`, then holds a slot
+/// until dropped. Moving the permit into the worker thread (rather than
+/// decrementing by hand at the end of the closure) means the slot is
+/// released on `Drop` even if the handler panics and the thread unwinds, so a
+/// panicking `f` can't wedge the pool for every later chunk.
+struct ThreadPermit {
+    pool: Arc<(Mutex<usize>, Condvar)>,
+}
+
+impl ThreadPermit {
+    fn acquire(pool: Arc<(Mutex<usize>, Condvar)>, max_threads: usize) -> Self {
+        let (count, cvar) = &*pool;
+        let mut count = cvar
+            .wait_while(count.lock().unwrap(), |n| *n >= max_threads)
+            .unwrap();
+        *count += 1;
+        drop(count);
+        ThreadPermit { pool }
+    }
+}
+
+impl Drop for ThreadPermit {
+    fn drop(&mut self) {
+        let (count, cvar) = &*self.pool;
+        *count.lock().unwrap() -= 1;
+        cvar.notify_one();
+    }
+}
+
+/// Runtime-configurable settings for the threaded `splitter_no_deps` family.
+///
+/// `TRESHOLD` used to conflate two different knobs: the minimum input length
+/// before splitting kicks in at all, and the size of each unit of work handed
+/// to a thread. `Splitter` separates them so callers can say "only
+/// parallelize above 10k elements, but then use 256-element chunks" instead
+/// of being stuck with one constant for both.
+pub struct Splitter {
+    threshold: usize,
+    chunk_size: usize,
+    max_threads: usize,
+}
+
+impl Default for Splitter {
+    fn default() -> Self {
+        Splitter {
+            threshold: TRESHOLD,
+            chunk_size: TRESHOLD,
+            max_threads: default_max_threads(),
+        }
+    }
+}
+
+impl Splitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Minimum input length before any threads are spawned.
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Work granularity per thread. Clamped to at least 1: `0` would make
+    /// `Vec::chunks` panic on any non-empty input.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Cap on live worker threads, enforced with the same `Condvar` semaphore
+    /// as [`splitter_no_deps_with_max_threads`]. Clamped to at least 1: `0`
+    /// would make the semaphore's `wait_while` predicate always true and
+    /// deadlock every worker.
+    pub fn max_threads(mut self, max_threads: usize) -> Self {
+        self.max_threads = max_threads.max(1);
+        self
+    }
+
+    /// Parallel map over `input`, splitting into `self.chunk_size` chunks once
+    /// `input.len()` reaches `self.threshold`, bounded to `self.max_threads`
+    /// concurrent worker threads.
+    pub fn map<T, R, F>(&self, input: Vec<T>, f: F) -> Vec<R>
+    where
+        T: Clone + Send + Sync + 'static,
+        R: Send + 'static,
+        F: Fn(T) -> R + Send + Sync + 'static,
+    {
+        if input.len() < self.threshold {
+            return input.iter().map(|v| f(v.clone())).collect();
+        }
+
+        let f = Arc::new(f);
+        let pool = Arc::new((Mutex::new(0usize), Condvar::new()));
         let mut handles = vec![];
         let mut res: Vec<R> = vec![];
-        for chunk in input.chunks(TRESHOLD) {
+        for chunk in input.chunks(self.chunk_size) {
             let (tx, rx) = channel::<Vec<T>>();
             let syncable = Arc::new(Mutex::new(rx));
+            let f = Arc::clone(&f);
+            let permit = ThreadPermit::acquire(Arc::clone(&pool), self.max_threads);
+
             handles.push(thread::spawn(move || {
+                let _permit = permit;
                 syncable
                     .lock()
                     .unwrap()
@@ -73,6 +175,265 @@ where
     }
 }
 
+pub fn splitter_no_deps<T, R, F>(input: Vec<T>, f: F) -> Vec<R>
+where
+    T: Clone + Send + Sync + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    Splitter::new().map(input, f)
+}
+
+/// Same as [`splitter_no_deps`], but never lets more than `max_threads` worker
+/// threads run at once. A `Condvar`-guarded counter acts as a semaphore: a
+/// chunk's thread is only spawned once the live count drops below the cap,
+/// and the thread decrements the count and wakes the next waiter when it's done.
+pub fn splitter_no_deps_with_max_threads<T, R, F>(
+    input: Vec<T>,
+    f: F,
+    max_threads: usize,
+) -> Vec<R>
+where
+    T: Clone + Send + Sync + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    Splitter::new().max_threads(max_threads).map(input, f)
+}
+
+/// Like [`splitter_no_deps`], but instead of blocking until every chunk has
+/// joined, returns a `Receiver` that yields `(chunk_index, Vec<R>)` as soon as
+/// each chunk's thread finishes - in completion order, not submission order.
+/// Callers who don't care about order can drain the receiver directly; those
+/// who want the original ordering back can pass it to [`collect_ordered`].
+///
+/// Below `TRESHOLD` this runs synchronously and sends a single chunk; above
+/// it, a background thread farms the chunks out through the same kind of
+/// `Condvar`-bounded pool as [`splitter_no_deps`]: each chunk still gets its
+/// own thread, but no more than the pool's cap are alive at once.
+pub fn splitter_streaming<T, R, F>(input: Vec<T>, f: F) -> mpsc::Receiver<(usize, Vec<R>)>
+where
+    T: Clone + Send + Sync + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    let (tx, rx) = channel::<(usize, Vec<R>)>();
+
+    if input.len() < TRESHOLD {
+        let result = input.iter().map(|v| f(v.clone())).collect::<Vec<R>>();
+        let _ = tx.send((0, result));
+        return rx;
+    }
+
+    let f = Arc::new(f);
+    let pool = Arc::new((Mutex::new(0usize), Condvar::new()));
+    let max_threads = default_max_threads();
+
+    // Dispatch from a background thread so that waiting for a free permit
+    // never blocks the caller, who gets `rx` back straight away.
+    thread::spawn(move || {
+        for (chunk_index, chunk) in input.chunks(TRESHOLD).enumerate() {
+            let chunk = chunk.to_vec();
+            let f = Arc::clone(&f);
+            let tx = tx.clone();
+            let permit = ThreadPermit::acquire(Arc::clone(&pool), max_threads);
+
+            thread::spawn(move || {
+                let _permit = permit;
+                let result = chunk.iter().map(|v| f(v.clone())).collect::<Vec<R>>();
+                // The receiver may already be gone if the caller dropped it early.
+                let _ = tx.send((chunk_index, result));
+            });
+        }
+    });
+
+    rx
+}
+
+/// Drains a [`splitter_streaming`] receiver and reassembles its chunks into a
+/// single `Vec<R>` in original input order.
+pub fn collect_ordered<R>(rx: mpsc::Receiver<(usize, Vec<R>)>) -> Vec<R> {
+    let mut chunks: Vec<(usize, Vec<R>)> = rx.iter().collect();
+    chunks.sort_by_key(|(chunk_index, _)| *chunk_index);
+    chunks.into_iter().flat_map(|(_, v)| v).collect()
+}
+
+/// Grants threads exclusive, runtime-verified access to disjoint slices of a
+/// `Vec<T>`, so several threads can mutate different parts of the same vector
+/// without a `Mutex` around the whole thing. A guard is only handed out if its
+/// range doesn't overlap any range already outstanding.
+struct RangeLock<T> {
+    data: UnsafeCell<Vec<T>>,
+    granted: Mutex<Vec<Range<usize>>>,
+}
+
+// Safety: access to `data` is only ever exposed through a `RangeGuard`, and
+// guards are only granted over disjoint ranges, so concurrent guards never
+// alias the same elements.
+unsafe impl<T: Send> Sync for RangeLock<T> {}
+
+impl<T> RangeLock<T> {
+    fn new(data: Vec<T>) -> Self {
+        RangeLock {
+            data: UnsafeCell::new(data),
+            granted: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Grants exclusive access to `range`. Panics if it overlaps a range that
+    /// is already checked out. A fixed, non-overlapping tiling (as used by
+    /// [`splitter_in_place`]) never trips this; the check exists to catch
+    /// misuse if this primitive is reused elsewhere.
+    fn acquire(self: &Arc<Self>, range: Range<usize>) -> RangeGuard<T> {
+        let mut granted = self.granted.lock().unwrap();
+        assert!(
+            granted
+                .iter()
+                .all(|g| g.start >= range.end || g.end <= range.start),
+            "RangeLock: requested range {:?} overlaps an outstanding guard",
+            range
+        );
+        granted.push(range.clone());
+        drop(granted);
+        RangeGuard {
+            lock: Arc::clone(self),
+            range,
+        }
+    }
+
+    fn into_inner(self) -> Vec<T> {
+        self.data.into_inner()
+    }
+}
+
+/// Proof of exclusive access to `range` within a [`RangeLock`]'s vector.
+/// Releases the range when dropped.
+struct RangeGuard<T> {
+    lock: Arc<RangeLock<T>>,
+    range: Range<usize>,
+}
+
+impl<T> RangeGuard<T> {
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        // Safety: this guard is the only one covering `range`, so it's the
+        // sole writer for these indices for as long as it's alive.
+        unsafe {
+            let ptr = (*self.lock.data.get()).as_mut_ptr().add(self.range.start);
+            std::slice::from_raw_parts_mut(ptr, self.range.len())
+        }
+    }
+}
+
+impl<T> Drop for RangeGuard<T> {
+    fn drop(&mut self) {
+        let mut granted = self.lock.granted.lock().unwrap();
+        if let Some(pos) = granted.iter().position(|g| *g == self.range) {
+            granted.remove(pos);
+        }
+    }
+}
+
+/// In-place parallel map: mutates every element of `data` via `f` without
+/// cloning. `data` is partitioned into `TRESHOLD`-sized, non-overlapping
+/// slices and each slice is handed to its own thread through a [`RangeLock`],
+/// so this works for any `T`, `Clone` or not.
+pub fn splitter_in_place<T, F>(data: &mut Vec<T>, f: F)
+where
+    T: Send + Sync + 'static,
+    F: Fn(&mut T) + Send + Sync + 'static,
+{
+    let len = data.len();
+    if len < TRESHOLD {
+        data.iter_mut().for_each(&f);
+        return;
+    }
+
+    let lock = Arc::new(RangeLock::new(std::mem::take(data)));
+    let f = Arc::new(f);
+    let pool = Arc::new((Mutex::new(0usize), Condvar::new()));
+    let max_threads = default_max_threads();
+    let mut handles = vec![];
+
+    let mut start = 0;
+    while start < len {
+        let end = (start + TRESHOLD).min(len);
+        let mut guard = lock.acquire(start..end);
+        let f = Arc::clone(&f);
+        let permit = ThreadPermit::acquire(Arc::clone(&pool), max_threads);
+        handles.push(thread::spawn(move || {
+            let _permit = permit;
+            for v in guard.as_mut_slice() {
+                f(v);
+            }
+        }));
+        start = end;
+    }
+
+    for h in handles {
+        h.join().unwrap();
+    }
+
+    *data = Arc::try_unwrap(lock)
+        .unwrap_or_else(|_| panic!("RangeLock still shared after all workers joined"))
+        .into_inner();
+}
+
+/// Parallel map-reduce: maps each `TRESHOLD`-sized chunk of `input` in its own
+/// thread, folds each chunk down to a single `R` locally (starting from
+/// `identity`), then combines the per-chunk partials with the same `reduce`.
+/// This avoids ever materializing a full `Vec<R>`, which plain `splitter_*`
+/// map functions can't avoid for aggregations like sum, min/max, or count.
+///
+/// `reduce` must be associative, i.e. `reduce(a, reduce(b, c)) == reduce(reduce(a, b), c)`,
+/// since chunk boundaries are an implementation detail and must not change
+/// the result. `identity` must be `reduce`'s identity element.
+pub fn splitter_reduce<T, R, M, Rd>(input: Vec<T>, map: M, reduce: Rd, identity: R) -> R
+where
+    T: Send + 'static,
+    R: Send + Clone + 'static,
+    M: Fn(T) -> R + Send + Sync + 'static,
+    Rd: Fn(R, R) -> R + Send + Sync + 'static,
+{
+    if input.len() < TRESHOLD {
+        return input.into_iter().map(&map).fold(identity, &reduce);
+    }
+
+    let map = Arc::new(map);
+    let reduce = Arc::new(reduce);
+    let pool = Arc::new((Mutex::new(0usize), Condvar::new()));
+    let max_threads = default_max_threads();
+    let mut handles = vec![];
+
+    // `IntoIter` consumes elements from the front in O(1) each, unlike
+    // repeated `Vec::split_off` from the front, which re-copies the whole
+    // remaining tail on every chunk and turns this into O(n^2).
+    let mut remaining = input.into_iter();
+    loop {
+        let chunk: Vec<T> = remaining.by_ref().take(TRESHOLD).collect();
+        if chunk.is_empty() {
+            break;
+        }
+
+        let map = Arc::clone(&map);
+        let reduce = Arc::clone(&reduce);
+        let identity = identity.clone();
+        let permit = ThreadPermit::acquire(Arc::clone(&pool), max_threads);
+        handles.push(thread::spawn(move || {
+            let _permit = permit;
+            chunk
+                .into_iter()
+                .map(|v| map(v))
+                .fold(identity, |acc, r| reduce(acc, r))
+        }));
+    }
+
+    let mut total = identity;
+    for h in handles {
+        total = reduce(total, h.join().unwrap());
+    }
+    total
+}
+
 #[test]
 pub fn without_splitting() {
     let input = vec![10, 20, 30, 40, 50];
@@ -92,7 +453,7 @@ pub fn without_splitting() {
 
 #[test]
 pub fn with_splitting() {
-    let input = (0..100000).into_iter().collect::<Vec<u32>>();
+    let input = (0..100000).collect::<Vec<u32>>();
 
     let modifier = |x: u32| -> String { x.to_string() };
 
@@ -107,4 +468,106 @@ pub fn with_splitting() {
     assert_eq!(result_no_deps, correct);
 }
 
+#[test]
+pub fn with_capturing_closure() {
+    let input = vec![1, 2, 3, 4, 5];
+    let offset = 1000;
+
+    let modifier = move |x: u32| -> u32 { x + offset };
+
+    let result_rayon = splitter_with_rayon(input.clone(), modifier);
+    let result_no_deps = splitter_no_deps(input.clone(), modifier);
+
+    let correct = input.iter().map(|x| x + offset).collect::<Vec<u32>>();
+    assert_eq!(result_rayon, correct);
+    assert_eq!(result_no_deps, correct);
+}
+
+#[test]
+pub fn max_threads_caps_peak_concurrency() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    let input = (0..1_000u32).collect::<Vec<u32>>();
+    let max_threads = 4;
+
+    let live = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+
+    let live_for_closure = Arc::clone(&live);
+    let peak_for_closure = Arc::clone(&peak);
+    let modifier = move |x: u32| -> u32 {
+        let current = live_for_closure.fetch_add(1, Ordering::SeqCst) + 1;
+        peak_for_closure.fetch_max(current, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(5));
+        live_for_closure.fetch_sub(1, Ordering::SeqCst);
+        x
+    };
+
+    let result = splitter_no_deps_with_max_threads(input.clone(), modifier, max_threads);
+
+    assert_eq!(result, input);
+    assert!(peak.load(Ordering::SeqCst) <= max_threads);
+}
+
+#[test]
+pub fn streaming_results_reorder_to_original() {
+    let input = (0..1_000u32).collect::<Vec<u32>>();
+    let modifier = |x: u32| -> u32 { x * 2 };
+
+    let rx = splitter_streaming(input.clone(), modifier);
+    let result = collect_ordered(rx);
+
+    let correct = input.iter().map(|x| x * 2).collect::<Vec<u32>>();
+    assert_eq!(result, correct);
+}
+
+#[test]
+pub fn in_place_mutates_without_cloning() {
+    let mut input = (0..100000).collect::<Vec<u32>>();
+    let expected = input.iter().map(|x| x + 1).collect::<Vec<u32>>();
+
+    splitter_in_place(&mut input, |x| *x += 1);
+
+    assert_eq!(input, expected);
+}
+
+#[test]
+pub fn reduce_sums_in_parallel() {
+    let input = (0..100000u64).collect::<Vec<u64>>();
+    let expected: u64 = input.iter().sum();
+
+    let result = splitter_reduce(input, |x| x, |a, b| a + b, 0u64);
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+pub fn splitter_builder_uses_configured_threshold_and_chunk_size() {
+    let input = (0..1_000u32).collect::<Vec<u32>>();
+    let modifier = |x: u32| -> u32 { x * 2 };
+
+    let result = Splitter::new()
+        .threshold(500)
+        .chunk_size(37)
+        .map(input.clone(), modifier);
+
+    let correct = input.iter().map(|x| x * 2).collect::<Vec<u32>>();
+    assert_eq!(result, correct);
+}
+
+#[test]
+pub fn splitter_clamps_zero_chunk_size_and_max_threads() {
+    let input = (0..1_000u32).collect::<Vec<u32>>();
+    let modifier = |x: u32| -> u32 { x * 2 };
+
+    let result = Splitter::new()
+        .chunk_size(0)
+        .max_threads(0)
+        .map(input.clone(), modifier);
+
+    let correct = input.iter().map(|x| x * 2).collect::<Vec<u32>>();
+    assert_eq!(result, correct);
+}
+
 fn main() {}